@@ -1,12 +1,21 @@
+use std::collections::HashMap;
+use std::io::Read;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::str::FromStr;
 
-use iron::mime::Mime;
+use iron::headers::ContentType;
+use iron::mime::{Mime, SubLevel, TopLevel};
 use iron::prelude::*;
 use iron::status;
+use mandelbrot::{encode_png, parse_complex, parse_pair, render};
 use router::Router;
+use serde::{Deserialize, Serialize};
 use urlencoded::UrlEncodedBody;
 
+// The largest width or height we'll render, so a client can't ask us to
+// allocate an unbounded pixel buffer.
+const MAX_MANDELBROT_DIMENSION: usize = 2000;
+
 fn main() {
     const PORT: u16 = 8080;
     // Unfortunately `SocketAddr::new` and `Ipv4Addr::new` are not yet
@@ -18,6 +27,7 @@ fn main() {
     let mut router = Router::new();
     router.get("/", get_form, "home");
     router.post("/gcd", post_gcd, "gcd");
+    router.get("/mandelbrot", get_mandelbrot, "mandelbrot");
 
     println!("Server listening on http://{}...", address);
     Iron::new(router).http(address).unwrap();
@@ -42,7 +52,36 @@ fn get_form(_request: &mut Request) -> IronResult<Response> {
     Ok(response)
 }
 
+#[derive(Deserialize)]
+struct GcdRequest {
+    numbers: Vec<u64>,
+}
+
+#[derive(Serialize)]
+struct GcdResponse {
+    numbers: Vec<u64>,
+    gcd: u64,
+}
+
+#[derive(Serialize)]
+struct JsonError {
+    error: String,
+}
+
 fn post_gcd(request: &mut Request) -> IronResult<Response> {
+    let is_json = match request.headers.get::<ContentType>() {
+        Some(ContentType(Mime(TopLevel::Application, SubLevel::Json, _))) => true,
+        _ => false,
+    };
+
+    if is_json {
+        post_gcd_json(request)
+    } else {
+        post_gcd_form(request)
+    }
+}
+
+fn post_gcd_form(request: &mut Request) -> IronResult<Response> {
     let mut response = Response::new();
 
     let form_data = match request.get_ref::<UrlEncodedBody>() {
@@ -78,19 +117,148 @@ fn post_gcd(request: &mut Request) -> IronResult<Response> {
         }
     }
 
+    let d = match fold_gcd(&numbers) {
+        Ok(d) => d,
+        Err(message) => {
+            response.set_mut(status::BadRequest);
+            response.set_mut(format!("{}\n", message));
+            return Ok(response);
+        }
+    };
+
+    response.set_mut(status::Ok);
+    response.set_mut("text/html; charset=utf-8".parse::<Mime>().unwrap());
+    response.set_mut(format!("GCD of {:?} is <b>{}</b>\n", numbers, d));
+
+    Ok(response)
+}
+
+fn post_gcd_json(request: &mut Request) -> IronResult<Response> {
+    let mut body = String::new();
+
+    if let Err(e) = request.body.read_to_string(&mut body) {
+        return json_error(status::BadRequest, format!("failed to read request body: {}", e));
+    }
+
+    let payload: GcdRequest = match serde_json::from_str(&body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            return json_error(status::BadRequest, format!("failed to parse JSON body: {}", e));
+        }
+    };
+
+    let d = match fold_gcd(&payload.numbers) {
+        Ok(d) => d,
+        Err(message) => return json_error(status::BadRequest, message),
+    };
+
+    let body = GcdResponse {
+        numbers: payload.numbers,
+        gcd: d,
+    };
+
+    let mut response = Response::new();
+    response.set_mut(status::Ok);
+    response.set_mut("application/json".parse::<Mime>().unwrap());
+    response.set_mut(serde_json::to_string(&body).unwrap());
+
+    Ok(response)
+}
+
+fn json_error(code: status::Status, message: String) -> IronResult<Response> {
+    let mut response = Response::new();
+    response.set_mut(code);
+    response.set_mut("application/json".parse::<Mime>().unwrap());
+    response.set_mut(serde_json::to_string(&JsonError { error: message }).unwrap());
+
+    Ok(response)
+}
+
+/// Fold `numbers` through `gcd`, sharing validation and the fold itself
+/// between the form (`text/html`) and JSON (`application/json`) handlers.
+fn fold_gcd(numbers: &[u64]) -> Result<u64, String> {
+    if numbers.is_empty() {
+        return Err("`numbers` must not be empty".to_string());
+    }
+
+    if numbers.iter().any(|&n| n == 0) {
+        return Err("`numbers` must not contain zero".to_string());
+    }
+
     let mut d = numbers[0];
 
     for m in &numbers[1..] {
         d = gcd(d, *m);
     }
 
+    Ok(d)
+}
+
+fn get_mandelbrot(request: &mut Request) -> IronResult<Response> {
+    let query_pairs: HashMap<String, String> =
+        request.url.as_ref().query_pairs().into_owned().collect();
+
+    let dimensions = match query_pairs.get("dimensions") {
+        None => return bad_request("missing `dimensions` query parameter"),
+        Some(s) => s,
+    };
+
+    let bounds = match parse_pair::<usize>(dimensions, 'x') {
+        None => return bad_request("failed to parse `dimensions`, expected WIDTHxHEIGHT"),
+        Some(bounds) => bounds,
+    };
+
+    if bounds.0 == 0
+        || bounds.1 == 0
+        || bounds.0 > MAX_MANDELBROT_DIMENSION
+        || bounds.1 > MAX_MANDELBROT_DIMENSION
+    {
+        return bad_request(&format!(
+            "`dimensions` must be within 1..={0}x1..={0}",
+            MAX_MANDELBROT_DIMENSION
+        ));
+    }
+
+    let upper_left = match query_pairs.get("upper_left").and_then(|s| parse_complex(s)) {
+        None => return bad_request("missing or unparsable `upper_left` query parameter"),
+        Some(c) => c,
+    };
+
+    let lower_right = match query_pairs.get("lower_right").and_then(|s| parse_complex(s)) {
+        None => return bad_request("missing or unparsable `lower_right` query parameter"),
+        Some(c) => c,
+    };
+
+    let mut pixels_buffer = vec![0; bounds.0 * bounds.1 * 3];
+    render(&mut pixels_buffer, bounds, upper_left, lower_right);
+
+    let png_bytes = match encode_png(&pixels_buffer, bounds) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            let mut response = Response::new();
+            response.set_mut(status::InternalServerError);
+            response.set_mut("text/html; charset=utf-8".parse::<Mime>().unwrap());
+            response.set_mut(format!("failed to encode PNG: {}\n", e));
+            return Ok(response);
+        }
+    };
+
+    let mut response = Response::new();
     response.set_mut(status::Ok);
-    response.set_mut("text/html; charset=utf-8".parse::<Mime>().unwrap());
-    response.set_mut(format!("GCD of {:?} is <b>{}</b>\n", numbers, d));
+    response.set_mut("image/png".parse::<Mime>().unwrap());
+    response.set_mut(png_bytes);
 
     Ok(response)
 }
 
+fn bad_request(message: &str) -> IronResult<Response> {
+    let mut response = Response::new();
+    response.set_mut(status::BadRequest);
+    response.set_mut("text/html; charset=utf-8".parse::<Mime>().unwrap());
+    response.set_mut(format!("{}\n", message));
+    Ok(response)
+}
+
 fn gcd(mut m: u64, mut n: u64) -> u64 {
     assert!(m != 0 && n != 0);
     while m != 0 {
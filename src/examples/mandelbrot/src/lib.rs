@@ -0,0 +1,251 @@
+use std::fs::File;
+use std::io::Cursor;
+use std::str::FromStr;
+
+use num::Complex;
+
+use image::png::PNGEncoder;
+use image::ColorType;
+
+pub fn parse_pair<T: FromStr>(s: &str, delimiter: char) -> Option<(T, T)> {
+    match s.find(delimiter) {
+        None => None,
+        Some(index) => match (T::from_str(&s[..index]), T::from_str(&s[index + 1..])) {
+            (Ok(l), Ok(r)) => Some((l, r)),
+            _ => None,
+        },
+    }
+}
+
+#[test]
+fn test_parse_pair() {
+    assert_eq!(parse_pair::<i32>("", ','), None);
+    assert_eq!(parse_pair::<i32>("10,", ','), None);
+    assert_eq!(parse_pair::<i32>(",10", ','), None);
+    assert_eq!(parse_pair::<i32>("10,20", ','), Some((10, 20)));
+    assert_eq!(parse_pair::<i32>("10,20x", ','), None);
+    assert_eq!(parse_pair::<f64>("0.5x", 'x'), None);
+    assert_eq!(parse_pair::<f64>("0.5x1.5", 'x'), Some((0.5, 1.5)));
+}
+
+// Note: Rust has traits for specifying how types can be converted from
+// one to another – this can be an impl instead.
+pub fn parse_complex(s: &str) -> Option<Complex<f64>> {
+    match parse_pair(s, ',') {
+        Some((re, im)) => Some(Complex { re, im }),
+        None => None,
+    }
+}
+
+#[test]
+fn test_parse_complex() {
+    assert_eq!(
+        parse_complex("1.234,-0.0578"),
+        Some(Complex {
+            re: 1.234,
+            im: -0.0578
+        })
+    );
+    assert_eq!(parse_complex(",-0.94"), None);
+}
+
+/// Try to determine whether the complex number `c` is an element of the
+/// Mandelbrot set, clamping to at most `limit` iterations to check.
+///
+/// If `c` is not an element of the Mandelbrot set, `Some((i, z))` is
+/// returned where `i` is the number of iterations needed to escape the
+/// circle (radius 2, centered at origin), and `z` is the value reached on
+/// that iteration; the caller needs `z` to compute a fractional escape
+/// value for smooth coloring.
+///
+/// If `c` is in fact an element of the Mondelbrot set (i.e. we failed to
+/// prove that `c` is not an element of the Mandelbrot set within the
+/// iteration `limit`), `None` is returned.
+fn escape_time(c: Complex<f64>, limit: u32) -> Option<(u32, Complex<f64>)> {
+    let mut z = Complex { re: 0.0, im: 0.0 };
+    for i in 0..limit {
+        z = z * z + c;
+        if z.norm_sqr() > 4.0 {
+            return Some((i, z));
+        }
+    }
+
+    None
+}
+
+/// Turn an integer escape-time iteration count into a continuous value by
+/// measuring how far past the radius-2 escape circle `z` landed. This is
+/// what lets `palette` blend colors smoothly instead of banding at each
+/// integer iteration boundary.
+fn smooth_escape_value(iterations: u32, z: Complex<f64>, limit: u32) -> f64 {
+    let mu = iterations as f64 + 1.0 - (0.5 * z.norm_sqr().ln()).ln() / 2f64.ln();
+    mu.max(0.0).min(limit as f64)
+}
+
+#[test]
+fn test_smooth_escape_value() {
+    // `z.norm_sqr() == e.powi(2)` makes `ln(0.5 * ln(z.norm_sqr()))` zero,
+    // so `mu` reduces to exactly `iterations + 1`.
+    let z = Complex {
+        re: std::f64::consts::E,
+        im: 0.0,
+    };
+    assert_eq!(smooth_escape_value(5, z, 255), 6.0);
+}
+
+#[test]
+fn test_smooth_escape_value_clamps_to_zero() {
+    let z = Complex { re: 10.0, im: 0.0 };
+    assert_eq!(smooth_escape_value(0, z, 255), 0.0);
+}
+
+#[test]
+fn test_smooth_escape_value_clamps_to_limit() {
+    let z = Complex { re: 2.1, im: 0.0 };
+    assert_eq!(smooth_escape_value(255, z, 255), 255.0);
+}
+
+// Note: the function signature is lackluster at best. We can improve it
+// by using named structs in favor of anonymous tuples to help readability,
+// and `upper_left` and `lower_right` can be grouped together into some
+// struct, e.g. `ComplexPlaneConstraint`, as they usually are used together.
+pub fn pixel_to_complex(
+    bounds: (usize, usize),
+    pixel: (usize, usize),
+    upper_left: Complex<f64>,
+    lower_right: Complex<f64>,
+) -> Complex<f64> {
+    let (width, height) = (
+        lower_right.re - upper_left.re,
+        upper_left.im - lower_right.im,
+    );
+
+    let (col, row) = (pixel.0 as f64, pixel.1 as f64);
+    let (img_width, img_height) = (bounds.0 as f64, bounds.1 as f64);
+
+    Complex {
+        re: upper_left.re + col * width / img_width,
+        // Note that the pixel y coordinate *increases* from top to down, but
+        // our view of the complex plane has the y coordinate / imaginary
+        // component *decreasing* from top to down.
+        im: upper_left.im - row * height / img_height,
+    }
+}
+
+#[test]
+fn test_pixel_to_complex() {
+    assert_eq!(
+        pixel_to_complex(
+            (100, 100),
+            (25, 75),
+            Complex { re: -1.0, im: 1.0 },
+            Complex { re: 1.0, im: -1.0 }
+        ),
+        Complex { re: -0.5, im: -0.5 }
+    );
+}
+
+const ESCAPE_TIME_LIMIT: u32 = 255;
+
+pub fn render(
+    pixels: &mut [u8],
+    bounds: (usize, usize),
+    upper_left: Complex<f64>,
+    lower_right: Complex<f64>,
+) {
+    // Precondition: require three RGB bytes per pixel in the output
+    // image's resolution.
+    assert!(pixels.len() == bounds.0 * bounds.1 * 3);
+
+    for row in 0..bounds.1 {
+        for column in 0..bounds.0 {
+            let point = pixel_to_complex(bounds, (column, row), upper_left, lower_right);
+
+            let (r, g, b) = match escape_time(point, ESCAPE_TIME_LIMIT) {
+                None => (0, 0, 0),
+                Some((iterations, z)) => {
+                    palette(smooth_escape_value(iterations, z, ESCAPE_TIME_LIMIT), ESCAPE_TIME_LIMIT)
+                }
+            };
+
+            let offset = (row * bounds.0 + column) * 3;
+            pixels[offset] = r;
+            pixels[offset + 1] = g;
+            pixels[offset + 2] = b;
+        }
+    }
+}
+
+/// Map a normalized escape value `mu` (in `[0, limit]`) to an RGB color by
+/// linearly interpolating between a handful of key stops. This keeps the
+/// escape-time gradient smooth instead of banding at each integer
+/// iteration boundary.
+fn palette(mu: f64, limit: u32) -> (u8, u8, u8) {
+    const STOPS: [(f64, u8, u8, u8); 5] = [
+        (0.0, 0, 7, 100),
+        (0.16, 32, 107, 203),
+        (0.42, 237, 255, 255),
+        (0.6425, 255, 170, 0),
+        (1.0, 0, 2, 0),
+    ];
+
+    let t = (mu / limit as f64).max(0.0).min(1.0);
+
+    let (lower, upper) = STOPS
+        .windows(2)
+        .map(|window| (window[0], window[1]))
+        .find(|(lower, upper)| t >= lower.0 && t <= upper.0)
+        .unwrap_or((STOPS[STOPS.len() - 2], STOPS[STOPS.len() - 1]));
+
+    let ratio = (t - lower.0) / (upper.0 - lower.0);
+
+    let lerp = |a: u8, b: u8| -> u8 { (a as f64 + (b as f64 - a as f64) * ratio).round() as u8 };
+
+    (lerp(lower.1, upper.1), lerp(lower.2, upper.2), lerp(lower.3, upper.3))
+}
+
+#[test]
+fn test_palette_boundaries() {
+    assert_eq!(palette(0.0, 255), (0, 7, 100));
+    assert_eq!(palette(255.0, 255), (0, 2, 0));
+}
+
+fn write_image_with<W: std::io::Write>(
+    output: W,
+    pixels: &[u8],
+    bounds: (usize, usize),
+) -> Result<(), std::io::Error> {
+    let (width, height) = (bounds.0 as u32, bounds.1 as u32);
+
+    let color = if pixels.len() == bounds.0 * bounds.1 * 3 {
+        ColorType::RGB(8)
+    } else {
+        ColorType::Gray(8)
+    };
+
+    let encoder = PNGEncoder::new(output);
+    encoder.encode(pixels, width, height, color)?;
+
+    Ok(())
+}
+
+pub fn write_image(
+    filename: &str,
+    pixels: &[u8],
+    bounds: (usize, usize),
+) -> Result<(), std::io::Error> {
+    let output = File::create(filename)?;
+
+    write_image_with(output, pixels, bounds)
+}
+
+/// Encode `pixels` as a PNG in memory, returning the raw bytes instead of
+/// writing them out to a file, so HTTP handlers can stream them straight
+/// into a response body.
+pub fn encode_png(pixels: &[u8], bounds: (usize, usize)) -> Result<Vec<u8>, std::io::Error> {
+    let mut buffer = Cursor::new(Vec::new());
+
+    write_image_with(&mut buffer, pixels, bounds)?;
+
+    Ok(buffer.into_inner())
+}